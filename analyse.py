@@ -248,7 +248,12 @@ def load_scrobbles(state: AnalysisState) -> None:
             scrobbles_raw = load(scrobbles_file)
 
         # Flatten scrobble pages into a big list
-        flattened = [item for sublist in scrobbles_raw for item in sublist]
+        # (a page with a single scrobble can be a bare object instead of a list)
+        flattened = [
+            item
+            for sublist in scrobbles_raw
+            for item in (sublist if isinstance(sublist, list) else [sublist])
+        ]
         return flattened
 
     # TODO option to filter scrobbles by date (from, to)
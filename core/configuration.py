@@ -2,6 +2,7 @@ import logging
 from os import path, mkdir
 from toml import load
 from typing import Any, Optional
+from urllib.parse import urlparse
 
 from .exception import ConfigException
 
@@ -28,6 +29,24 @@ logging_name_to_level = {
 }
 
 
+def is_valid_http_url(url: str) -> bool:
+    """
+    Args:
+        url:
+            URL to check.
+
+    Returns:
+        Whether the URL parses and has an http(s) scheme and a host.
+    """
+    try:
+        parsed_url = urlparse(url)
+    except ValueError:
+        # e.g. unbalanced brackets in an IPv6 host
+        return False
+
+    return parsed_url.scheme in ("http", "https") and bool(parsed_url.netloc)
+
+
 class TOMLConfig:
     """
     General-purpose toml config class.
@@ -50,7 +69,7 @@ class TOMLConfig:
         if data is None and not ignore_empty:
             raise ConfigException(f"Configuration table missing: '{name}'")
 
-        return TOMLConfig(data)
+        return TOMLConfig(data if data is not None else {})
 
     def get(self, name: str, fallback: Any = None, ignore_empty: bool = False) -> Any:
         data = self.data.get(name)
@@ -71,7 +90,7 @@ class AnalysisConfig:
     __slots__ = (
         "_config",
         "_table_authentication", "_table_source_paths", "_table_dest_paths",
-        "_table_cache", "_table_logging", "_table_fuzzy", "_table_genres",
+        "_table_cache", "_table_logging", "_table_fuzzy", "_table_genres", "_table_network",
         # Authentication
         "LASTFM_API_KEY", "LASTFM_API_SECRET",
         # SourcePaths
@@ -86,7 +105,9 @@ class AnalysisConfig:
         "FUZZY_MIN_TITLE", "FUZZY_MIN_ALBUM", "FUZZY_MIN_ARTIST", "FUZZY_YOUTUBE_MIN_TITLE",
         # Genres
         "MIN_TAG_WEIGHT", "GENRES_USE_SPECIFIC", "MAX_GENRE_COUNT", "MIN_LASTFM_SIMILARITY",
-        "MAX_LASTFM_PAGES"
+        "MAX_LASTFM_PAGES",
+        # Network
+        "REQUEST_TIMEOUT_SECONDS", "LASTFM_API_URL",
     )
 
     def __init__(self, config_dict: TOMLConfig):
@@ -99,6 +120,8 @@ class AnalysisConfig:
         self._table_logging = self._config.get_table("Logging")
         self._table_fuzzy = self._config.get_table("FuzzyMatching")
         self._table_genres = self._config.get_table("Genres")
+        # Optional, older configuration files don't have this table
+        self._table_network = self._config.get_table("Network", ignore_empty=True)
 
         ##########
         # Authentication
@@ -173,6 +196,21 @@ class AnalysisConfig:
         self.MAX_GENRE_COUNT: int = int(self._table_genres.get("max_genre_count"))
         # self.GENRES_USE_SPECIFIC = self._table_genres.get("use_most_specific")
 
+        ##########
+        # Network
+        ##########
+        self.REQUEST_TIMEOUT_SECONDS: int = int(
+            self._table_network.get("request_timeout_seconds", fallback=30, ignore_empty=True)
+        )
+        if self.REQUEST_TIMEOUT_SECONDS < 1:
+            raise ConfigException("request_timeout_seconds must be at least 1")
+
+        self.LASTFM_API_URL: str = self._table_network.get(
+            "lastfm_api_url", fallback="https://ws.audioscrobbler.com/2.0/", ignore_empty=True
+        )
+        if not is_valid_http_url(self.LASTFM_API_URL):
+            raise ConfigException(f"lastfm_api_url is not a valid http(s) URL: '{self.LASTFM_API_URL}'")
+
 
 raw_config = TOMLConfig.from_filename(CONFIG_FILE)
 config = AnalysisConfig(raw_config)
@@ -79,20 +79,20 @@ def download_genre_data() -> None:
     log.info("Downloading genre data...")
 
     log.debug(f"Downloading genre list from {BEETS_GENRES_LIST_SRC}")
-    resp_list = requests.get(BEETS_GENRES_LIST_SRC)
+    resp_list = requests.get(BEETS_GENRES_LIST_SRC, timeout=config.REQUEST_TIMEOUT_SECONDS)
     with open(BEETS_GENRES_LIST_PATH, "wb") as genre_list_f:
         for chunk in resp_list.iter_content(chunk_size=256):
             genre_list_f.write(chunk)
 
     # DEPRECATED the genre tree is unused
     log.debug(f"Downloading genre tree from {BEETS_GENRES_TREE_SRC}")
-    resp_tree = requests.get(BEETS_GENRES_TREE_SRC)
+    resp_tree = requests.get(BEETS_GENRES_TREE_SRC, timeout=config.REQUEST_TIMEOUT_SECONDS)
     with open(BEETS_GENRES_TREE_PATH, "wb") as genre_tree_f:
         for chunk in resp_tree.iter_content(chunk_size=256):
             genre_tree_f.write(chunk)
 
     log.debug(f"Downloading Beets' MIT LICENSE notice from {BEETS_LICENSE_SRC}")
-    resp_license = requests.get(BEETS_LICENSE_SRC)
+    resp_license = requests.get(BEETS_LICENSE_SRC, timeout=config.REQUEST_TIMEOUT_SECONDS)
     with open(BEETS_LICENSE_PATH, "wb") as genre_license_f:
         for chunk in resp_license.iter_content(chunk_size=256):
             genre_license_f.write(chunk)
@@ -4,7 +4,7 @@ from urllib.parse import urlencode
 
 from typing import Dict, Optional
 
-from .configuration import PROJECT_NAME, VERSION, REPOSITORY
+from .configuration import config, PROJECT_NAME, VERSION, REPOSITORY
 
 log = logging.getLogger(__name__)
 
@@ -53,7 +53,7 @@ class ReleaseTrack:
 
         full_url = f"{BASE_MB_RELEASE_URL}?{urlencode(params)}"
         # Send query to musicbrainz and get the release with this track back
-        resp = req.get(full_url)
+        resp = req.get(full_url, timeout=config.REQUEST_TIMEOUT_SECONDS)
         data_raw = resp.json()
 
         d_release_count = data_raw.get("release-count")
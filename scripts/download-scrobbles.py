@@ -4,6 +4,12 @@
 # Usage:
 # When calling this script, pass your username with the parameters "--username [username]".
 # If no parameter is passed, you will be asked for the username interactively.
+# Optionally pass "--limit [amount]" to only download the latest [amount] scrobbles.
+# The currently playing track is skipped unless "--include-now-playing" is passed
+# (in that case it's saved with the download time as its scrobble time).
+# Pass "--no-extended" for lighter requests, but the track love column will then always be 0.
+# The saved JSON is compact by default, pass "--pretty" for indented, human-readable output ("--compact" to switch back).
+# For debugging, "--page [number]" downloads only that page and "--raw" prints its unparsed response body instead.
 #################
 import logging
 import time
@@ -12,13 +18,14 @@ import requests
 import sys
 import getopt
 import json
-from typing import Optional, List, Tuple, Dict
+from math import ceil
+from typing import Optional, List, Tuple, Dict, Union
 from urllib.parse import urlencode
 
 # Add the base directory as a path for the import
 sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..")))
 # Otherwise this won't work
-from core.configuration import config
+from core.configuration import config, is_valid_http_url
 
 logging.basicConfig(level=config.VERBOSITY)
 log: logging.Logger = logging.getLogger(__name__)
@@ -26,72 +33,275 @@ log: logging.Logger = logging.getLogger(__name__)
 # To avoid leaking your api key
 logging.getLogger("urllib3.connectionpool").setLevel(logging.INFO)
 
-LASTFM_API_URL = "https://ws.audioscrobbler.com/2.0/"
+# Transient Last.fm errors (operation failed, service offline, temporarily unavailable, rate limit exceeded)
+# See https://www.last.fm/api/errorcodes
+LASTFM_RETRYABLE_ERRORS = (8, 11, 16, 29)
+
+# Retry schedules as (maximum attempts, initial delay in seconds), the delay doubles after every attempt.
+# Connection failures are usually momentary (e.g. DNS hiccups on a cold start),
+# so they are retried more often and sooner than timeouts and server-side errors.
+MAX_REQUEST_ATTEMPTS = 5
+REQUEST_RETRY_INITIAL_DELAY = 2
+MAX_CONNECTION_ATTEMPTS = 8
+CONNECTION_RETRY_INITIAL_DELAY = 1
+
+RetrySchedule = Tuple[int, int]
+REQUEST_RETRY_SCHEDULE: RetrySchedule = (MAX_REQUEST_ATTEMPTS, REQUEST_RETRY_INITIAL_DELAY)
+CONNECTION_RETRY_SCHEDULE: RetrySchedule = (MAX_CONNECTION_ATTEMPTS, CONNECTION_RETRY_INITIAL_DELAY)
 
 # Parse command line arguments
 username: Optional[str] = None
+limit: Optional[int] = None
+include_now_playing: bool = False
+extended_data: bool = True
+single_page: Optional[int] = None
+raw_output: bool = False
+api_base_url: str = config.LASTFM_API_URL
+pretty_output: bool = False
+opts: List[Tuple[str, str]]
+args: List[str]
 try:
-    if len(sys.argv[1:]) < 1:
-        raise getopt.GetoptError("no args")
-
-    opts: List[Tuple[str, str]]
-    args: List[Tuple[str, str]]
-    opts, args = getopt.getopt(sys.argv[1:], "u:", "username=")
-
-    for opt, arg in opts:
-        if opt in ("-u", "--username"):
-            username = arg
-except getopt.GetoptError:
-    # Fall back to interactive
+    opts, args = getopt.getopt(
+        sys.argv[1:], "u:l:",
+        ["username=", "limit=", "include-now-playing", "no-extended", "page=", "raw", "api-base-url=", "pretty", "compact"]
+    )
+except getopt.GetoptError as getopt_error:
+    log.error(f"Invalid arguments: {getopt_error}")
+    sys.exit(1)
+
+if args:
+    log.error(f"Unexpected arguments: {' '.join(args)}")
+    sys.exit(1)
+
+for opt, arg in opts:
+    if opt in ("-u", "--username"):
+        username = arg
+    elif opt in ("-l", "--limit"):
+        try:
+            limit = int(arg)
+        except ValueError:
+            limit = None
+
+        if limit is None or limit < 1:
+            log.error("-l [amount]/--limit [amount] must be a positive integer.")
+            sys.exit(1)
+    elif opt == "--include-now-playing":
+        include_now_playing = True
+    elif opt == "--no-extended":
+        extended_data = False
+    elif opt == "--page":
+        try:
+            single_page = int(arg)
+        except ValueError:
+            single_page = None
+
+        if single_page is None or single_page < 1:
+            log.error("--page [number] must be a positive integer.")
+            sys.exit(1)
+    elif opt == "--raw":
+        raw_output = True
+    elif opt == "--pretty":
+        pretty_output = True
+    elif opt == "--compact":
+        pretty_output = False
+    elif opt == "--api-base-url":
+        # Intentionally undocumented above, meant for testing against a proxy or mock server
+        if not is_valid_http_url(arg):
+            log.error(f"--api-base-url is not a valid http(s) URL: '{arg}'")
+            sys.exit(1)
+
+        api_base_url = arg
+
+if username is None:
+    if opts:
+        log.error("Missing -u [username]/--username [username].")
+        sys.exit(1)
+
+    # Fall back to interactive only when no arguments were passed at all
     log.warning("No -u [username]/--username [username] passed, falling back to interactive.")
     username = input("Enter your username:")
 
 log.info(f"Chosen username: {username}")
+if api_base_url != config.LASTFM_API_URL:
+    log.info(f"Using Last.fm API URL: {api_base_url}")
+if limit is not None:
+    log.info(f"Limiting download to the latest {limit} scrobbles.")
 
+# Last.fm doesn't allow more than 200 tracks per page
+results_per_page: int = min(200, limit) if limit is not None else 200
 
-def request_page(lastfm_username: str, page_num: int) -> dict:
+
+def classify_request_failure(failure: Union[dict, Exception],
+                             status_code: Optional[int] = None) -> Tuple[str, Optional[RetrySchedule]]:
+    """
+    Describes a failed Last.fm request and decides whether it's transient and worth retrying.
+
+    Args:
+        failure:
+            Either the exception raised while requesting/decoding the response
+            or the decoded Last.fm API error response.
+        status_code:
+            HTTP status code of the response, if one was received.
+
+    Returns:
+        A tuple of a human-readable description and the retry schedule to use (None if the request
+        shouldn't be retried). Connection errors use CONNECTION_RETRY_SCHEDULE, while timeouts,
+        non-JSON bodies with a 5xx status (usually HTML error pages during outages)
+        and Last.fm errors from LASTFM_RETRYABLE_ERRORS use REQUEST_RETRY_SCHEDULE.
+    """
+    # Exception messages aren't used because they contain the request URL (and with it, the api key)
+    if isinstance(failure, (requests.exceptions.InvalidURL, requests.exceptions.MissingSchema,
+                            requests.exceptions.InvalidSchema, requests.exceptions.InvalidHeader)):
+        # These also subclass ValueError, but retrying an invalid request won't fix it
+        return f"invalid request ({type(failure).__name__})", None
+    elif isinstance(failure, requests.exceptions.ConnectionError):
+        # Checked before Timeout because ConnectTimeout is both
+        return "could not connect to Last.fm", CONNECTION_RETRY_SCHEDULE
+    elif isinstance(failure, requests.exceptions.Timeout):
+        return f"no response within {config.REQUEST_TIMEOUT_SECONDS} seconds", REQUEST_RETRY_SCHEDULE
+    elif isinstance(failure, json.JSONDecodeError):
+        is_server_error = status_code is not None and 500 <= status_code < 600
+        return f"response is not valid JSON (HTTP {status_code})", REQUEST_RETRY_SCHEDULE if is_server_error else None
+    elif isinstance(failure, Exception):
+        return f"request failed ({type(failure).__name__})", None
+
+    error_code: Optional[int] = failure.get("error")
+    return (f"Last.fm returned error {error_code} ({failure.get('message')})",
+            REQUEST_RETRY_SCHEDULE if error_code in LASTFM_RETRYABLE_ERRORS else None)
+
+
+def build_page_url(lastfm_username: str, page_num: int) -> str:
     parameters = {
         "api_key": config.LASTFM_API_KEY,
         "format": "json",
         "method": "user.getRecentTracks",
-        "limit": "200",
+        "limit": str(results_per_page),
         "user": lastfm_username,
         "page": page_num,
-        "extended": 1
+        "extended": 1 if extended_data else 0
     }
 
-    full_url = LASTFM_API_URL + "?" + urlencode(parameters)
-    resp = requests.get(full_url)
+    return api_base_url + "?" + urlencode(parameters)
 
-    return resp.json()
 
+def request_page(lastfm_username: str, page_num: int) -> dict:
+    full_url = build_page_url(lastfm_username, page_num)
+
+    attempt: int = 0
+    while True:
+        attempt += 1
+        failure: Union[dict, Exception]
+        status_code: Optional[int] = None
+        try:
+            resp = requests.get(full_url, timeout=config.REQUEST_TIMEOUT_SECONDS)
+            status_code = resp.status_code
+            data: dict = resp.json()
+        except (requests.exceptions.RequestException, ValueError) as request_error:
+            failure = request_error
+        else:
+            if data.get("error") is None:
+                return data
+
+            failure = data
+
+        failure_description, retry_schedule = classify_request_failure(failure, status_code)
+        if retry_schedule is None or attempt >= retry_schedule[0]:
+            log.error(f"Requesting page {page_num} failed: {failure_description}")
+            sys.exit(1)
+
+        max_attempts, initial_delay = retry_schedule
+        retry_delay: int = initial_delay * 2 ** (attempt - 1)
+        log.warning(f"Requesting page {page_num} failed: {failure_description}, "
+                    f"retrying in {retry_delay} seconds (attempt {attempt}/{max_attempts})")
+        time.sleep(retry_delay)
+
+
+def get_page_tracks(recenttracks: dict) -> List[dict]:
+    """
+    Last.fm sometimes returns a single track as a bare object instead of a one-element list,
+    so this normalizes the "track" value to a list.
+    """
+    tracks = recenttracks.get("track") or []
+    if isinstance(tracks, dict):
+        return [tracks]
+
+    return tracks
+
+
+def is_now_playing(track: dict) -> bool:
+    return (track.get("@attr") or {}).get("nowplaying") == "true"
+
+
+if raw_output:
+    # Print the response body as-is, without any parsing or retrying
+    raw_page: int = single_page if single_page is not None else 1
+    log.info(f"Requesting page {raw_page} (raw output).")
+
+    try:
+        raw_resp = requests.get(build_page_url(username, raw_page), timeout=config.REQUEST_TIMEOUT_SECONDS)
+    except requests.exceptions.RequestException as raw_error:
+        log.error(f"Requesting page {raw_page} failed: {classify_request_failure(raw_error)[0]}")
+        sys.exit(1)
+
+    log.info(f"HTTP {raw_resp.status_code}, Content-Type: {raw_resp.headers.get('Content-Type')}")
+    print(raw_resp.text)
+    sys.exit(0)
 
 scrobbles_pages: List[List[dict]] = []
-page_counter: int = 1
+page_counter: int = single_page if single_page is not None else 1
 
 # Request first page and find the total number of pages
-log.info("Requesting first page.")
+log.info(f"Requesting page {page_counter}.")
 
 first_request: Dict = request_page(username, page_counter)
 recenttracks_raw: Dict = first_request.get("recenttracks") or {}
-scrobbles_pages.append(recenttracks_raw.get("track") or [])
+first_page_tracks: List[dict] = get_page_tracks(recenttracks_raw)
+
+# Only the first page can contain the currently playing track (filtering is a no-op on other pages)
+if include_now_playing:
+    for track in first_page_tracks:
+        if is_now_playing(track):
+            # Now-playing tracks don't have a scrobble time yet
+            track["date"] = {"uts": str(int(time.time()))}
+else:
+    first_page_tracks = [track for track in first_page_tracks if not is_now_playing(track)]
+
+scrobbles_pages.append(first_page_tracks)
 
 total_pages: int = int(recenttracks_raw.get("@attr").get("totalPages"))
-log.info(f"Total pages: {total_pages}, downloading...")
+if single_page is not None:
+    if single_page > total_pages:
+        log.error(f"Page {single_page} doesn't exist, {username} only has {total_pages} pages.")
+        sys.exit(1)
 
-# Request the rest of the pages
+    log.info(f"Downloaded page {single_page}/{total_pages}, skipping the rest.")
+    total_pages = single_page
+else:
+    if limit is not None:
+        total_pages = min(total_pages, ceil(limit / results_per_page))
+    log.info(f"Total pages: {total_pages}, downloading...")
+
+# Request the rest of the pages (the first one is already downloaded)
+page_counter += 1
 while page_counter <= total_pages:
     log.info(f"Requesting page {page_counter}/{total_pages}")
 
     new_page: dict = request_page(username, page_counter)
     recenttracks_raw: dict = new_page.get("recenttracks") or {}
 
-    tracks: List[dict] = recenttracks_raw.get("track") or []
+    tracks: List[dict] = get_page_tracks(recenttracks_raw)
     scrobbles_pages.append(tracks)
 
     page_counter += 1
     time.sleep(0.2)
 
+if limit is not None:
+    # The first page can contain an additional now-playing track, so trim by count
+    tracks_left: int = limit
+    for page_index, page_tracks in enumerate(scrobbles_pages):
+        scrobbles_pages[page_index] = page_tracks[:tracks_left]
+        tracks_left -= len(scrobbles_pages[page_index])
+
 # Write the results to json file
 filename: str = os.path.abspath(
     os.path.join(
@@ -99,11 +309,13 @@ filename: str = os.path.abspath(
         "..",
         "data",
         f"scrobbles-{username}-{int(time.time())}.json"
+        if single_page is None
+        else f"scrobbles-{username}-page{single_page}-{int(time.time())}.json"
     )
 )
 log.info(f"Saving scrobbles to file: {filename}")
 
 with open(filename, "w", encoding="utf8") as sc_out:
-    json.dump(scrobbles_pages, sc_out, ensure_ascii=False)
+    json.dump(scrobbles_pages, sc_out, ensure_ascii=False, indent=2 if pretty_output else None)
 
 log.info("DONE")